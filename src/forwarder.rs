@@ -18,21 +18,164 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
-//! Manage the device forwarder operation.
+//! Manage the device forwarder operations.
+//!
+//! Each forwarder session request names one [`Operation`] (an interactive terminal, a file
+//! transfer, ...). Only [`Operation::Terminal`] sessions are serviced; any other operation is
+//! reported as unsupported, whatever [`Capabilities`] this device was compiled with.
 
 use std::collections::{hash_map::Entry, HashMap};
 use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::data::Publisher;
 use astarte_device_sdk::types::AstarteType;
-use astarte_device_sdk::{AstarteDeviceDataEvent, FromEvent};
+use astarte_device_sdk::{Aggregation, AstarteDeviceDataEvent, FromEvent};
+use async_trait::async_trait;
 use edgehog_forwarder::astarte::SessionInfo;
 use edgehog_forwarder::connections_manager::{ConnectionsManager, Disconnected};
 use log::{debug, error, info};
+use once_cell::sync::Lazy;
+use rand::Rng;
 use reqwest::Url;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
 
 const FORWARDER_SESSION_STATE_INTERFACE: &str = "io.edgehog.devicemanager.ForwarderSessionState";
+const FORWARDER_SESSION_CAPABILITIES_INTERFACE: &str =
+    "io.edgehog.devicemanager.ForwarderSessionCapabilities";
+const FORWARDER_SESSION_REQUEST_INTERFACE: &str =
+    "io.edgehog.devicemanager.ForwarderSessionRequest";
+
+/// Extract the token of the session to close out of a [`FORWARDER_SESSION_REQUEST_INTERFACE`]
+/// close command.
+fn session_token_to_close(astarte_event: AstarteDeviceDataEvent) -> Option<String> {
+    let Aggregation::Object(mut fields) = astarte_event.data else {
+        return None;
+    };
+
+    match fields.remove("session_token") {
+        Some(AstarteType::String(token)) => Some(token),
+        _ => None,
+    }
+}
+
+/// Remote operation requested for a forwarder session, gated by [`Capabilities`].
+///
+/// New variants can be added over time; older hosts that don't send an `operation` field are
+/// assumed to want [`Operation::Terminal`], so existing fleets keep working unmodified.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+enum Operation {
+    /// Interactive shell / command spawn, optionally run inside an explicit shell path.
+    #[default]
+    Terminal,
+    /// File read/write/transfer and permission changes.
+    FileTransfer,
+}
+
+impl Operation {
+    /// Capability this operation is gated behind, see [`Capabilities`].
+    fn capability(self) -> &'static str {
+        match self {
+            Self::Terminal => Capabilities::TERMINAL,
+            Self::FileTransfer => Capabilities::FILE_TRANSFER,
+        }
+    }
+
+    /// Parse the requested operation out of a session-open event, defaulting to
+    /// [`Operation::Terminal`] when the field is missing or unrecognized.
+    fn from_event(astarte_event: &AstarteDeviceDataEvent) -> Self {
+        let Aggregation::Object(fields) = &astarte_event.data else {
+            return Self::default();
+        };
+
+        match fields.get("operation") {
+            Some(AstarteType::String(op)) if op == Capabilities::FILE_TRANSFER => {
+                Self::FileTransfer
+            }
+            _ => Self::default(),
+        }
+    }
+}
+
+/// Parse the remote host's forwarder protocol version out of a session-open event, if present.
+///
+/// Older hosts that don't advertise a version yet are treated as compatible, so devices can be
+/// rolled out ahead of the Edgehog hosts they connect to.
+fn remote_version_from_event(
+    astarte_event: &AstarteDeviceDataEvent,
+) -> Result<Option<Version>, ForwarderError> {
+    let Aggregation::Object(fields) = &astarte_event.data else {
+        return Ok(None);
+    };
+
+    match fields.get("protocol_version") {
+        Some(AstarteType::String(version)) => Ok(Some(Version::parse(version)?)),
+        _ => Ok(None),
+    }
+}
+
+/// Default delay before the first reconnect attempt.
+const DEFAULT_BASE_DELAY: Duration = Duration::from_secs(1);
+/// Default cap on the reconnect delay.
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(60);
+/// Default number of reconnect attempts before giving up on a session.
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+
+/// Exponential backoff (with jitter) policy driving the forwarder's reconnect loop.
+#[derive(Debug, Clone, Copy)]
+struct ReconnectPolicy {
+    base_delay: Duration,
+    max_delay: Duration,
+    max_attempts: u32,
+}
+
+impl ReconnectPolicy {
+    /// Delay before the `attempt`-th reconnect (1-indexed): `base_delay * 2^(attempt - 1)`,
+    /// clamped to `max_delay`, with random jitter in `[0, delay/2]` added to avoid a
+    /// thundering-herd reconnection across a fleet.
+    fn delay(&self, attempt: u32) -> Duration {
+        let exp = 2u32
+            .checked_pow(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let delay = self.base_delay.saturating_mul(exp).min(self.max_delay);
+
+        let jitter_ms = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 2);
+
+        delay + Duration::from_millis(jitter_ms)
+    }
+}
+
+/// Version of the forwarder wire protocol implemented by this crate.
+///
+/// Derived from the crate's own version at compile time and compared against the host's version,
+/// carried in the session-open event (see [`remote_version_from_event`]), before a session is
+/// connected.
+static PROTOCOL_VERSION: Lazy<Version> = Lazy::new(|| {
+    Version::new(
+        env!("CARGO_PKG_VERSION_MAJOR")
+            .parse()
+            .expect("invalid major version"),
+        env!("CARGO_PKG_VERSION_MINOR")
+            .parse()
+            .expect("invalid minor version"),
+        env!("CARGO_PKG_VERSION_PATCH")
+            .parse()
+            .expect("invalid patch version"),
+    )
+});
+
+/// Check whether a remote forwarder protocol version is compatible with [`PROTOCOL_VERSION`].
+///
+/// Two versions are compatible when they share the same major number and the remote minor is
+/// less than or equal to ours; the patch number is ignored.
+fn is_compatible_with(remote: &Version) -> bool {
+    PROTOCOL_VERSION.major == remote.major && remote.minor <= PROTOCOL_VERSION.minor
+}
 
 /// Forwarder errors
 #[derive(displaydoc::Display, thiserror::Error, Debug)]
@@ -45,13 +188,33 @@ pub enum ForwarderError {
 
     /// Connections manager error
     ConnectionsManager(#[from] edgehog_forwarder::connections_manager::Error),
+
+    /// invalid protocol version received from the host, {0}
+    InvalidVersion(#[from] semver::Error),
+
+    /// session store error
+    SessionStore(#[from] SessionStoreError),
+}
+
+/// Errors returned by a [`SessionStore`] implementation.
+#[derive(displaydoc::Display, thiserror::Error, Debug)]
+pub enum SessionStoreError {
+    /// I/O error accessing the session store, {0}
+    Io(#[from] std::io::Error),
+
+    /// could not (de)serialize the session store, {0}
+    Serde(#[from] serde_json::Error),
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 enum SessionStatus {
     Connecting,
     Connected,
     Disconnected,
+    /// The remote host speaks an incompatible forwarder protocol version.
+    Incompatible,
+    /// This device can't service the requested [`Operation`], see [`Capabilities::supports`].
+    Unsupported,
 }
 
 impl Display for SessionStatus {
@@ -60,6 +223,8 @@ impl Display for SessionStatus {
             Self::Connecting => write!(f, "Connecting"),
             Self::Connected => write!(f, "Connected"),
             Self::Disconnected => write!(f, "Disconnected"),
+            Self::Incompatible => write!(f, "Incompatible"),
+            Self::Unsupported => write!(f, "Unsupported"),
         }
     }
 }
@@ -92,14 +257,29 @@ impl SessionState {
             status: SessionStatus::Disconnected,
         }
     }
+
+    fn incompatible(token: String) -> Self {
+        Self {
+            token,
+            status: SessionStatus::Incompatible,
+        }
+    }
+
+    fn unsupported(token: String) -> Self {
+        Self {
+            token,
+            status: SessionStatus::Unsupported,
+        }
+    }
 }
 
 impl From<SessionState> for AstarteType {
     fn from(value: SessionState) -> Self {
         match value.status {
-            SessionStatus::Connecting | SessionStatus::Connected => {
-                Self::String(value.status.to_string())
-            }
+            SessionStatus::Connecting
+            | SessionStatus::Connected
+            | SessionStatus::Incompatible
+            | SessionStatus::Unsupported => Self::String(value.status.to_string()),
             SessionStatus::Disconnected => Self::Unset,
         }
     }
@@ -120,38 +300,329 @@ impl SessionState {
     }
 }
 
+/// Broad operations a forwarder session can service.
+///
+/// Advertised to the host so it can gate UI/features on what the device was compiled with,
+/// rather than probing the device and failing. The list holds every operation compiled into this
+/// binary; which of them a session can actually be opened for is decided by
+/// [`supports`](Self::supports), which accepts only [`Operation::Terminal`]. New operations can
+/// be added over time without breaking older devices, since unknown entries are simply ignored by
+/// the host.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Capabilities(Vec<&'static str>);
+
+impl Capabilities {
+    const TERMINAL: &'static str = "terminal";
+    const FILE_TRANSFER: &'static str = "file-transfer";
+    const PORT_FORWARD: &'static str = "port-forward";
+
+    /// Build the set of capabilities compiled into this binary.
+    fn detect() -> Self {
+        let mut caps = vec![Self::TERMINAL];
+
+        if cfg!(feature = "forwarder-file-transfer") {
+            caps.push(Self::FILE_TRANSFER);
+        }
+
+        if cfg!(feature = "forwarder-port-forward") {
+            caps.push(Self::PORT_FORWARD);
+        }
+
+        Self(caps)
+    }
+
+    /// Send the capabilities for a session to Astarte.
+    async fn send<P>(&self, publisher: &P, token: &str) -> Result<(), astarte_device_sdk::Error>
+    where
+        P: Publisher + 'static + Send + Sync,
+    {
+        let ipath = format!("/{token}/capabilities");
+        let idata = AstarteType::from(self.clone());
+
+        publisher
+            .send(FORWARDER_SESSION_CAPABILITIES_INTERFACE, &ipath, idata)
+            .await
+    }
+
+    /// Whether this binary can actually service the given [`Operation`].
+    ///
+    /// `ConnectionsManager` doesn't yet expose a way to route frames per [`Operation`] (see the
+    /// `NOTE` in [`Forwarder::connect`]), so only [`Operation::Terminal`] is ever really serviced,
+    /// regardless of which other capabilities this binary advertises. Without this, a host talking
+    /// to a binary compiled with `forwarder-file-transfer` would have a `FileTransfer` session
+    /// accepted and reported `Connected`, even though it's serviced identically to a terminal one.
+    fn supports(&self, op: Operation) -> bool {
+        op == Operation::Terminal && self.0.contains(&op.capability())
+    }
+}
+
+impl From<Capabilities> for AstarteType {
+    fn from(value: Capabilities) -> Self {
+        Self::StringArray(value.0.into_iter().map(String::from).collect())
+    }
+}
+
+/// A live forwarder session's connection information and last-known status, persisted so that
+/// it can be restored across a runtime restart or crash.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct SessionRecord {
+    host: String,
+    port: u16,
+    token: String,
+    secure: bool,
+    status: SessionStatus,
+    /// Operation the session was opened for. Defaults to [`Operation::Terminal`] when reading
+    /// records persisted before operations existed.
+    #[serde(default)]
+    operation: Operation,
+}
+
+impl SessionRecord {
+    fn new(sinfo: &SessionInfo, op: Operation, status: SessionStatus) -> Self {
+        Self {
+            host: sinfo.host.clone(),
+            port: sinfo.port,
+            token: sinfo.session_token.clone(),
+            secure: sinfo.secure,
+            status,
+            operation: op,
+        }
+    }
+
+    fn session_info(&self) -> SessionInfo {
+        SessionInfo {
+            host: self.host.clone(),
+            port: self.port,
+            session_token: self.token.clone(),
+            secure: self.secure,
+        }
+    }
+}
+
+/// Storage for live [`SessionRecord`]s, so that in-flight remote sessions survive a runtime
+/// restart or crash instead of being silently dropped.
+///
+/// Kept as a trait, mirroring how [`Publisher`] is mocked today, so tests can swap in an
+/// in-memory implementation instead of touching disk.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+trait SessionStore {
+    /// Persist (or update) the record for a session.
+    async fn save(&self, record: SessionRecord) -> Result<(), SessionStoreError>;
+
+    /// Remove a session's record, e.g. once it has genuinely disconnected.
+    async fn remove(&self, token: &str) -> Result<(), SessionStoreError>;
+
+    /// Load every currently persisted session record.
+    async fn load(&self) -> Result<Vec<SessionRecord>, SessionStoreError>;
+
+    /// Return an independent handle to the same store, so each spawned session task can hold
+    /// its own, mirroring how a [`Publisher`] is cloned per session.
+    fn clone(&self) -> Self
+    where
+        Self: Sized;
+}
+
+/// Default path backing [`FileSessionStore`] when the forwarder is initialized through
+/// [`Forwarder::init`].
+const SESSION_STORE_PATH: &str = "/var/lib/edgehog-device-runtime/forwarder-sessions.json";
+
+/// Default, file-backed [`SessionStore`] implementation.
+///
+/// Keeps the whole set of live sessions as a single JSON-encoded file on disk. Every spawned
+/// session task holds its own [`clone`](SessionStore::clone) of the store pointing at the same
+/// file, so the load-modify-write cycle in [`save`](Self::save)/[`remove`](Self::remove) is
+/// serialized behind a shared lock to avoid two concurrent writers clobbering each other's
+/// records.
+#[derive(Debug)]
+struct FileSessionStore {
+    path: PathBuf,
+    // Shared (not per-clone) so every handle to the same file serializes through the same lock.
+    lock: Arc<Mutex<()>>,
+}
+
+impl FileSessionStore {
+    fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    async fn write_all(&self, records: &[SessionRecord]) -> Result<(), SessionStoreError> {
+        let data = serde_json::to_vec(records)?;
+
+        tokio::fs::write(&self.path, data).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn save(&self, record: SessionRecord) -> Result<(), SessionStoreError> {
+        let _guard = self.lock.lock().await;
+
+        let mut records = self.load().await?;
+        records.retain(|r| r.token != record.token);
+        records.push(record);
+
+        self.write_all(&records).await
+    }
+
+    async fn remove(&self, token: &str) -> Result<(), SessionStoreError> {
+        let _guard = self.lock.lock().await;
+
+        let mut records = self.load().await?;
+        records.retain(|r| r.token != token);
+
+        self.write_all(&records).await
+    }
+
+    async fn load(&self) -> Result<Vec<SessionRecord>, SessionStoreError> {
+        match tokio::fs::read(&self.path).await {
+            Ok(data) if data.is_empty() => Ok(Vec::new()),
+            Ok(data) => match serde_json::from_slice(&data) {
+                Ok(records) => Ok(records),
+                Err(err) => {
+                    // A crash can leave a truncated or corrupt file behind; treating it as empty
+                    // keeps startup resilient instead of bricking the forwarder on a bad restart.
+                    error!(
+                        "corrupt session store at {}, treating it as empty, {err}",
+                        self.path.display()
+                    );
+
+                    Ok(Vec::new())
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            lock: Arc::clone(&self.lock),
+        }
+    }
+}
+
 /// Device forwarder.
 ///
 /// It maintains a collection of tokio task handles, each one identified by a [`Key`] containing
-/// the connection information and responsible for providing forwarder functionalities. For
-/// instance, a task could open a remote terminal between the device and a certain host.
+/// the connection information and responsible for providing forwarder functionalities. Each task
+/// is gated to the [`Operation`] the session was requested for: an interactive remote terminal is
+/// serviced, any other operation is reported as unsupported and the session is closed. Live
+/// sessions are mirrored to a [`SessionStore`] so they can be restored if the runtime restarts.
 #[derive(Debug)]
-pub struct Forwarder<P> {
+pub struct Forwarder<P, S = FileSessionStore> {
     publisher: P,
     tasks: HashMap<SessionInfo, JoinHandle<()>>,
+    capabilities: Capabilities,
+    store: S,
+    /// Delay before the first reconnect attempt of a dropped session.
+    pub base_delay: Duration,
+    /// Cap on the exponential reconnect delay, however many attempts have been made; the random
+    /// jitter (up to half the capped delay) is added on top of it.
+    pub max_delay: Duration,
+    /// Number of reconnect attempts allowed before a session is given up on.
+    pub max_attempts: u32,
 }
 
-impl<P> Forwarder<P> {
+impl<P> Forwarder<P, FileSessionStore> {
+    /// Initialize the forwarder, restoring any session persisted to the default, file-backed
+    /// store across a runtime restart or crash.
     pub async fn init(publisher: P) -> Result<Self, ForwarderError>
     where
         P: Publisher + 'static + Send + Sync,
     {
-        // unset all the existing sessions
-        // TODO: the following snippet assumes that the property has been stored, which is not the case until the [issue #346](https://github.com/edgehog-device-manager/edgehog-device-runtime/issues/346) is solved
-        debug!("unsetting ForwarderSessionState property");
-        for prop in publisher
-            .interface_props(FORWARDER_SESSION_STATE_INTERFACE)
-            .await?
-        {
-            debug!("unset {}", &prop.path);
-            publisher
-                .unset(FORWARDER_SESSION_STATE_INTERFACE, &prop.path)
-                .await?;
+        Self::init_with_store(publisher, FileSessionStore::new(SESSION_STORE_PATH)).await
+    }
+}
+
+impl<P, S> Forwarder<P, S> {
+    /// Initialize the forwarder with an injectable [`SessionStore`], e.g. an in-memory one in
+    /// tests, restoring any session it has persisted across a runtime restart or crash.
+    pub async fn init_with_store(publisher: P, store: S) -> Result<Self, ForwarderError>
+    where
+        P: Publisher + 'static + Send + Sync,
+        S: SessionStore + 'static + Send + Sync,
+    {
+        let policy = ReconnectPolicy {
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        };
+
+        let mut tasks = HashMap::new();
+
+        for record in store.load().await? {
+            match record.status {
+                // the session was still live when the runtime stopped, respawn it instead of
+                // dropping it on the floor
+                SessionStatus::Connecting | SessionStatus::Connected => {
+                    info!("restoring session {}", record.token);
+
+                    let sinfo = record.session_info();
+                    let edgehog_url = match Url::try_from(&sinfo) {
+                        Ok(url) => url,
+                        Err(err) => {
+                            error!("invalid url, {err}");
+                            continue;
+                        }
+                    };
+                    let publisher = publisher.clone();
+                    let store = store.clone();
+
+                    let op = record.operation;
+                    tasks.insert(
+                        sinfo.clone(),
+                        tokio::spawn(async move {
+                            if let Err(err) = Self::handle_session(
+                                edgehog_url,
+                                sinfo,
+                                op,
+                                // the host's version was already validated when this session was
+                                // first connected
+                                None,
+                                publisher,
+                                Capabilities::detect(),
+                                store,
+                                policy,
+                                true,
+                            )
+                            .await
+                            {
+                                error!("session failed, {err}");
+                            }
+                        }),
+                    );
+                }
+                // the session was genuinely disconnected, nothing to restore: just make sure the
+                // property and the store agree
+                SessionStatus::Disconnected
+                | SessionStatus::Incompatible
+                | SessionStatus::Unsupported => {
+                    debug!("dropping stale session {}", record.token);
+
+                    SessionState::disconnected(record.token.clone())
+                        .send(&publisher)
+                        .await?;
+
+                    store.remove(&record.token).await?;
+                }
+            }
         }
 
         Ok(Self {
             publisher,
-            tasks: HashMap::default(),
+            tasks,
+            capabilities: Capabilities::detect(),
+            store,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
         })
     }
 
@@ -159,7 +630,19 @@ impl<P> Forwarder<P> {
     pub fn handle_sessions(&mut self, astarte_event: AstarteDeviceDataEvent)
     where
         P: Publisher + 'static + Send + Sync,
+        S: SessionStore + 'static + Send + Sync,
     {
+        // the operation and protocol version fields must be read before the event is consumed
+        // below
+        let op = Operation::from_event(&astarte_event);
+        let remote_version = match remote_version_from_event(&astarte_event) {
+            Ok(remote_version) => remote_version,
+            Err(err) => {
+                error!("invalid protocol version in session request, {err}");
+                return;
+            }
+        };
+
         // retrieve the Url that the device must use to open a WebSocket connection with a host
         let sinfo = match SessionInfo::from_event(astarte_event) {
             Ok(sinfo) => sinfo,
@@ -180,16 +663,31 @@ impl<P> Forwarder<P> {
 
         // check if the remote terminal task is already running. if not, spawn a new task and add it
         // to the collection
-        // flag indicating whether the connection should use TLS, i.e. 'ws' or 'wss' scheme.
-        let secure = sinfo.secure;
-        let session_token = sinfo.session_token.clone();
         let publisher = self.publisher.clone();
-        self.get_running(sinfo).or_insert_with(|| {
+        let capabilities = self.capabilities.clone();
+        let store = self.store.clone();
+        let policy = ReconnectPolicy {
+            base_delay: self.base_delay,
+            max_delay: self.max_delay,
+            max_attempts: self.max_attempts,
+        };
+        self.get_running(sinfo).or_insert_with_key(|sinfo| {
             info!("opening a new session");
+            let sinfo = sinfo.clone();
             // spawn a new task responsible for handling the remote terminal operations
             tokio::spawn(async move {
-                if let Err(err) =
-                    Self::handle_session(edgehog_url, session_token, secure, publisher).await
+                if let Err(err) = Self::handle_session(
+                    edgehog_url,
+                    sinfo,
+                    op,
+                    remote_version,
+                    publisher,
+                    capabilities,
+                    store,
+                    policy,
+                    false,
+                )
+                .await
                 {
                     error!("session failed, {err}");
                 }
@@ -205,32 +703,136 @@ impl<P> Forwarder<P> {
         self.tasks.entry(sinfo)
     }
 
+    /// Force-close a running forwarder session on the host's request.
+    ///
+    /// Looks up the task handling the given session token, aborts it and waits for the
+    /// cancellation to complete, then reports the session as disconnected since an aborted task
+    /// can no longer do so itself.
+    pub async fn handle_session_close(&mut self, astarte_event: AstarteDeviceDataEvent)
+    where
+        P: Publisher + 'static + Send + Sync,
+        S: SessionStore + 'static + Send + Sync,
+    {
+        let Some(token) = session_token_to_close(astarte_event) else {
+            error!("missing or invalid session_token in the close request");
+            return;
+        };
+
+        // remove finished tasks first, so a session whose task already ended is not found below
+        // and is neither aborted nor reported again
+        self.tasks.retain(|_, jh| !jh.is_finished());
+
+        let Some(sinfo) = self
+            .tasks
+            .keys()
+            .find(|sinfo| sinfo.session_token == token)
+            .cloned()
+        else {
+            debug!("no running session for token {token}, nothing to close");
+            return;
+        };
+
+        // the task is still in the map and unfinished, since we just retained it above
+        let jh = self
+            .tasks
+            .remove(&sinfo)
+            .expect("session token was just found in the map");
+
+        jh.abort();
+
+        if let Err(err) = jh.await {
+            if !err.is_cancelled() {
+                error!("session task panicked while closing, {err}");
+            }
+        }
+
+        if let Err(err) = SessionState::disconnected(token.clone())
+            .send(&self.publisher)
+            .await
+        {
+            error!("failed to unset session state, {err}");
+        }
+
+        if let Err(err) = self.store.remove(&token).await {
+            error!("failed to remove closed session from the store, {err}");
+        }
+    }
+
     /// Handle remote session connection, operations and disconnection.
     async fn handle_session(
         edgehog_url: Url,
-        session_token: String,
-        secure: bool,
+        sinfo: SessionInfo,
+        op: Operation,
+        remote_version: Option<Version>,
         publisher: P,
+        capabilities: Capabilities,
+        store: S,
+        policy: ReconnectPolicy,
+        resuming: bool,
     ) -> Result<(), ForwarderError>
     where
         P: Publisher + 'static + Send + Sync,
+        S: SessionStore + 'static + Send + Sync,
     {
-        // update the session state to "Connecting"
-        SessionState::connecting(session_token.clone())
-            .send(&publisher)
-            .await?;
+        let session_token = sinfo.session_token.clone();
+        let mut rejected = false;
 
-        if let Err(err) =
-            Self::connect(edgehog_url, session_token.clone(), secure, &publisher).await
-        {
-            error!("failed to connect, {err}");
+        if let Some(remote_version) = &remote_version {
+            if !is_compatible_with(remote_version) {
+                error!(
+                    "incompatible forwarder protocol version, local {}, remote {remote_version}",
+                    *PROTOCOL_VERSION
+                );
+
+                SessionState::incompatible(session_token.clone())
+                    .send(&publisher)
+                    .await?;
+
+                rejected = true;
+            }
+        }
+
+        if !rejected && !capabilities.supports(op) {
+            error!("device doesn't support operation {op:?} for session {session_token}");
+
+            SessionState::unsupported(session_token.clone())
+                .send(&publisher)
+                .await?;
+
+            rejected = true;
         }
 
-        // unset the session state, meaning that the device correctly disconnected itself
+        if !rejected {
+            if !resuming {
+                // update the session state to "Connecting"
+                SessionState::connecting(session_token.clone())
+                    .send(&publisher)
+                    .await?;
+            }
+
+            store
+                .save(SessionRecord::new(&sinfo, op, SessionStatus::Connecting))
+                .await?;
+
+            // let the host know which operations this device can service for the session
+            capabilities.send(&publisher, &session_token).await?;
+
+            if let Err(err) =
+                Self::connect(edgehog_url, sinfo, op, &publisher, &store, policy).await
+            {
+                error!("failed to connect, {err}");
+            }
+        }
+
+        // unset the session state: whether the session was rejected above (`Incompatible` /
+        // `Unsupported`) or actually ran, it must not stay set on Astarte forever, and the store
+        // must not keep a record of a session that's no longer live.
         SessionState::disconnected(session_token.clone())
             .send(&publisher)
             .await?;
 
+        store.remove(&session_token).await?;
+
         info!("forwarder correctly disconnected");
 
         Ok(())
@@ -238,39 +840,87 @@ impl<P> Forwarder<P> {
 
     async fn connect(
         edgehog_url: Url,
-        session_token: String,
-        secure: bool,
+        sinfo: SessionInfo,
+        op: Operation,
         publisher: &P,
+        store: &S,
+        policy: ReconnectPolicy,
     ) -> Result<(), ForwarderError>
     where
         P: Publisher + 'static + Send + Sync,
+        S: SessionStore + 'static + Send + Sync,
     {
+        let session_token = sinfo.session_token.clone();
+        let secure = sinfo.secure;
+
         let mut con_manager = ConnectionsManager::connect(edgehog_url.clone(), secure).await?;
 
         // update the session state to "Connected"
         SessionState::connected(session_token.clone())
             .send(publisher)
             .await?;
+        store
+            .save(SessionRecord::new(&sinfo, op, SessionStatus::Connected))
+            .await?;
 
         // handle the connections
-        while let Err(Disconnected(err)) = con_manager.handle_connections().await {
-            error!("WebSocket disconnected, {err}");
+        //
+        // NOTE: `ConnectionsManager` doesn't yet expose a way to route frames per `Operation`, so
+        // every session reaching this point is serviced as a terminal one; `op` is only persisted
+        // so a restored session keeps remembering what it was opened for, and is otherwise unused
+        // here. `Capabilities::supports` already rejects anything other than `Operation::Terminal`
+        // with `Unsupported` before a session ever reaches `connect`, so this can't silently
+        // misrepresent a session it doesn't actually know how to service. Real per-operation frame
+        // dispatch depends on that support landing in the `edgehog_forwarder` crate.
+        while let Err(Disconnected(mut last_err)) = con_manager.handle_connections().await {
+            error!("WebSocket disconnected, {last_err}");
 
             // in case of a websocket error, the connection has been lost, so update the session
             // state to "Connecting"
             SessionState::connecting(session_token.clone())
                 .send(publisher)
                 .await?;
+            store
+                .save(SessionRecord::new(&sinfo, op, SessionStatus::Connecting))
+                .await?;
 
-            con_manager
-                .reconnect()
-                .await
-                .map_err(ForwarderError::ConnectionsManager)?;
+            let mut reconnected = false;
+
+            for attempt in 1..=policy.max_attempts {
+                let delay = policy.delay(attempt);
+                debug!(
+                    "reconnect attempt {attempt}/{} in {delay:?}",
+                    policy.max_attempts
+                );
+                tokio::time::sleep(delay).await;
+
+                match con_manager.reconnect().await {
+                    Ok(()) => {
+                        reconnected = true;
+                        break;
+                    }
+                    Err(err) => {
+                        error!("reconnect attempt {attempt} failed, {err}");
+                        last_err = err;
+                    }
+                }
+            }
+
+            if !reconnected {
+                error!(
+                    "giving up reconnecting after {} attempts",
+                    policy.max_attempts
+                );
+                return Err(ForwarderError::ConnectionsManager(last_err));
+            }
 
             // update the session state to "Connected" since connection has been re-established
             SessionState::connected(session_token.clone())
                 .send(publisher)
                 .await?;
+            store
+                .save(SessionRecord::new(&sinfo, op, SessionStatus::Connected))
+                .await?;
         }
 
         Ok(())
@@ -281,9 +931,8 @@ impl<P> Forwarder<P> {
 mod tests {
     use super::*;
     use crate::data::tests::MockPublisher;
-    use astarte_device_sdk::store::StoredProp;
-    use astarte_device_sdk::{interface::def::Ownership, Aggregation};
     use std::net::Ipv4Addr;
+    use std::time::Duration;
 
     #[test]
     fn test_session_status() {
@@ -291,9 +940,17 @@ mod tests {
             SessionStatus::Connected,
             SessionStatus::Connecting,
             SessionStatus::Disconnected,
+            SessionStatus::Incompatible,
+            SessionStatus::Unsupported,
         ]
         .map(|ss| ss.to_string());
-        let exp_res = ["Connected", "Connecting", "Disconnected"];
+        let exp_res = [
+            "Connected",
+            "Connecting",
+            "Disconnected",
+            "Incompatible",
+            "Unsupported",
+        ];
 
         // test display
         for (idx, el) in sstatus.into_iter().enumerate() {
@@ -301,6 +958,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_compatible_with() {
+        // same major and minor: compatible
+        assert!(is_compatible_with(&Version::new(
+            PROTOCOL_VERSION.major,
+            PROTOCOL_VERSION.minor,
+            PROTOCOL_VERSION.patch + 1
+        )));
+
+        // older remote minor: compatible, newer devices can still talk to older hosts
+        if PROTOCOL_VERSION.minor > 0 {
+            assert!(is_compatible_with(&Version::new(
+                PROTOCOL_VERSION.major,
+                PROTOCOL_VERSION.minor - 1,
+                0
+            )));
+        }
+
+        // newer remote minor: incompatible, the host speaks a protocol we don't understand yet
+        assert!(!is_compatible_with(&Version::new(
+            PROTOCOL_VERSION.major,
+            PROTOCOL_VERSION.minor + 1,
+            0
+        )));
+
+        // different major: incompatible regardless of minor
+        assert!(!is_compatible_with(&Version::new(
+            PROTOCOL_VERSION.major + 1,
+            0,
+            0
+        )));
+    }
+
     #[test]
     fn test_session_state() {
         let sstates = [
@@ -366,84 +1056,172 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    fn session_open_event(operation: Option<&str>) -> AstarteDeviceDataEvent {
+        let mut fields = HashMap::from([
+            (
+                "host".to_string(),
+                AstarteType::String("127.0.0.1".to_string()),
+            ),
+            ("port".to_string(), AstarteType::Integer(8080)),
+            (
+                "session_token".to_string(),
+                AstarteType::String("abcd".to_string()),
+            ),
+            ("secure".to_string(), AstarteType::Boolean(false)),
+        ]);
+
+        if let Some(operation) = operation {
+            fields.insert(
+                "operation".to_string(),
+                AstarteType::String(operation.to_string()),
+            );
+        }
+
+        AstarteDeviceDataEvent {
+            interface: FORWARDER_SESSION_STATE_INTERFACE.to_string(),
+            path: "/request".to_string(),
+            data: Aggregation::Object(fields),
+        }
+    }
+
+    #[test]
+    fn test_operation_from_event() {
+        // older hosts that don't send an operation are assumed to want a terminal
+        assert_eq!(
+            Operation::from_event(&session_open_event(None)),
+            Operation::Terminal
+        );
+
+        assert_eq!(
+            Operation::from_event(&session_open_event(Some(Capabilities::TERMINAL))),
+            Operation::Terminal
+        );
+        assert_eq!(
+            Operation::from_event(&session_open_event(Some(Capabilities::FILE_TRANSFER))),
+            Operation::FileTransfer
+        );
+        assert_eq!(
+            Operation::from_event(&session_open_event(Some("bogus"))),
+            Operation::Terminal
+        );
+    }
+
+    #[test]
+    fn test_capabilities_supports() {
+        let capabilities = Capabilities(vec![Capabilities::TERMINAL]);
+
+        assert!(capabilities.supports(Operation::Terminal));
+        assert!(!capabilities.supports(Operation::FileTransfer));
+
+        // even if a binary is compiled in to advertise `FileTransfer`, it can't actually be
+        // serviced yet (no per-operation dispatch), so it must still be reported unsupported
+        let capabilities = Capabilities(vec![Capabilities::TERMINAL, Capabilities::FILE_TRANSFER]);
+
+        assert!(capabilities.supports(Operation::Terminal));
+        assert!(!capabilities.supports(Operation::FileTransfer));
+    }
+
+    fn with_protocol_version(
+        mut event: AstarteDeviceDataEvent,
+        version: &str,
+    ) -> AstarteDeviceDataEvent {
+        let Aggregation::Object(fields) = &mut event.data else {
+            unreachable!()
+        };
+
+        fields.insert(
+            "protocol_version".to_string(),
+            AstarteType::String(version.to_string()),
+        );
+
+        event
+    }
+
+    #[test]
+    fn test_remote_version_from_event() {
+        // older hosts that don't advertise a version are treated as compatible
+        assert_eq!(
+            remote_version_from_event(&session_open_event(None)).unwrap(),
+            None
+        );
+
+        let event = with_protocol_version(session_open_event(None), "1.2.3");
+        assert_eq!(
+            remote_version_from_event(&event).unwrap(),
+            Some(Version::new(1, 2, 3))
+        );
+
+        let event = with_protocol_version(session_open_event(None), "not-a-version");
+        assert!(matches!(
+            remote_version_from_event(&event),
+            Err(ForwarderError::InvalidVersion(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_init_forwarder() {
-        let mut publisher = MockPublisher::new();
-        mock_forwarder_init(&mut publisher);
-        let f = Forwarder::init(publisher).await;
+        // no persisted sessions to restore
+        let publisher = MockPublisher::new();
+        let mut store = MockSessionStore::new();
+        store.expect_load().returning(|| Ok(Vec::new()));
+
+        let f = Forwarder::init_with_store(publisher, store).await;
 
         assert!(f.is_ok());
 
-        // test when an error is returned by the publisher
-        let mut publisher = MockPublisher::new();
+        // test when an error is returned by the store
+        let publisher = MockPublisher::new();
+        let mut store = MockSessionStore::new();
 
-        publisher
-            .expect_interface_props()
-            .withf(move |iface: &str| iface == FORWARDER_SESSION_STATE_INTERFACE)
-            .returning(|_: &str| {
-                // the returned error is irrelevant, it is only necessary to the test
-                Err(astarte_device_sdk::error::Error::ConnectionTimeout)
-            });
+        store.expect_load().returning(|| {
+            // the returned error is irrelevant, it is only necessary to the test
+            Err(SessionStoreError::Io(std::io::Error::other("boom")))
+        });
 
-        let f = Forwarder::init(publisher).await;
+        let f = Forwarder::init_with_store(publisher, store).await;
 
         assert!(f.is_err());
 
+        // a session that was genuinely disconnected is dropped from both Astarte and the store
         let mut publisher = MockPublisher::new();
+        let mut store = MockSessionStore::new();
 
-        publisher
-            .expect_interface_props()
-            .withf(move |iface: &str| iface == FORWARDER_SESSION_STATE_INTERFACE)
-            .returning(|_: &str| {
-                Ok(vec![StoredProp {
-                    interface: FORWARDER_SESSION_STATE_INTERFACE.to_string(),
-                    path: "/abcd/status".to_string(),
-                    value: AstarteType::String("Connected".to_string()),
-                    interface_major: 0,
-                    ownership: Ownership::Device,
-                }])
-            });
+        store.expect_load().returning(|| {
+            Ok(vec![SessionRecord {
+                host: Ipv4Addr::LOCALHOST.to_string(),
+                port: 8080,
+                token: "abcd".to_string(),
+                secure: false,
+                status: SessionStatus::Disconnected,
+                operation: Operation::Terminal,
+            }])
+        });
+        store
+            .expect_remove()
+            .withf(|token| token == "abcd")
+            .returning(|_| Ok(()));
 
         publisher
-            .expect_unset()
-            .withf(move |iface, ipath| {
-                iface == "io.edgehog.devicemanager.ForwarderSessionState" && ipath == "/abcd/status"
+            .expect_send()
+            .withf(move |iface, ipath, idata| {
+                iface == FORWARDER_SESSION_STATE_INTERFACE
+                    && ipath == "/abcd/status"
+                    && idata == &AstarteType::Unset
             })
-            // the returned error is irrelevant, it is only necessary to the test
-            .returning(|_, _| Err(astarte_device_sdk::error::Error::ConnectionTimeout));
-
-        let f = Forwarder::init(publisher).await;
-
-        assert!(f.is_err());
-    }
+            .returning(|_, _, _| Ok(()));
 
-    fn mock_forwarder_init(publisher: &mut MockPublisher) {
-        publisher
-            .expect_interface_props()
-            .withf(move |iface: &str| iface == FORWARDER_SESSION_STATE_INTERFACE)
-            .returning(|_: &str| {
-                Ok(vec![StoredProp {
-                    interface: FORWARDER_SESSION_STATE_INTERFACE.to_string(),
-                    path: "/abcd/status".to_string(),
-                    value: AstarteType::String("Connected".to_string()),
-                    interface_major: 0,
-                    ownership: Ownership::Device,
-                }])
-            });
+        let f = Forwarder::init_with_store(publisher, store).await;
 
-        publisher
-            .expect_unset()
-            .withf(move |iface, ipath| {
-                iface == "io.edgehog.devicemanager.ForwarderSessionState" && ipath == "/abcd/status"
-            })
-            .returning(|_, _| Ok(()));
+        assert!(f.is_ok());
     }
 
     #[tokio::test]
     async fn test_handle_sessions() {
         let mut publisher = MockPublisher::new();
+        let mut store = MockSessionStore::new();
 
         publisher.expect_clone().returning(MockPublisher::new);
+        store.expect_clone().returning(MockSessionStore::new);
 
         let mut f = Forwarder {
             publisher,
@@ -456,6 +1234,11 @@ mod tests {
                 },
                 tokio::spawn(async {}),
             )]),
+            capabilities: Capabilities::detect(),
+            store,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
         };
 
         let astarte_event = AstarteDeviceDataEvent {
@@ -478,4 +1261,182 @@ mod tests {
         // the test is successful once handle_sessions terminates
         f.handle_sessions(astarte_event);
     }
+
+    fn close_event(token: &str) -> AstarteDeviceDataEvent {
+        AstarteDeviceDataEvent {
+            interface: FORWARDER_SESSION_REQUEST_INTERFACE.to_string(),
+            path: "/request".to_string(),
+            data: Aggregation::Object(HashMap::from([(
+                "session_token".to_string(),
+                AstarteType::String(token.to_string()),
+            )])),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_session_close_token_not_found() {
+        let publisher = MockPublisher::new();
+        let store = MockSessionStore::new();
+
+        let mut f = Forwarder {
+            publisher,
+            tasks: HashMap::new(),
+            capabilities: Capabilities::detect(),
+            store,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        };
+
+        // neither the publisher nor the store are expected to be touched
+        f.handle_session_close(close_event("abcd")).await;
+    }
+
+    #[tokio::test]
+    async fn test_handle_session_close_already_finished() {
+        let publisher = MockPublisher::new();
+        let store = MockSessionStore::new();
+
+        let mut f = Forwarder {
+            publisher,
+            tasks: HashMap::from([(
+                SessionInfo {
+                    host: Ipv4Addr::LOCALHOST.to_string(),
+                    port: 8080,
+                    session_token: "abcd".to_string(),
+                    secure: false,
+                },
+                tokio::spawn(async {}),
+            )]),
+            capabilities: Capabilities::detect(),
+            store,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        };
+
+        // give the spawned no-op task a chance to finish before it's looked up
+        tokio::task::yield_now().await;
+
+        // the stale entry is reaped instead of being treated as a running session
+        f.handle_session_close(close_event("abcd")).await;
+
+        assert!(f.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handle_session_close_running_session() {
+        let mut publisher = MockPublisher::new();
+        let mut store = MockSessionStore::new();
+
+        publisher
+            .expect_send()
+            .withf(move |iface, ipath, idata| {
+                iface == FORWARDER_SESSION_STATE_INTERFACE
+                    && ipath == "/abcd/status"
+                    && idata == &AstarteType::Unset
+            })
+            .returning(|_, _, _| Ok(()));
+
+        store
+            .expect_remove()
+            .withf(|token| token == "abcd")
+            .returning(|_| Ok(()));
+
+        let mut f = Forwarder {
+            publisher,
+            tasks: HashMap::from([(
+                SessionInfo {
+                    host: Ipv4Addr::LOCALHOST.to_string(),
+                    port: 8080,
+                    session_token: "abcd".to_string(),
+                    secure: false,
+                },
+                tokio::spawn(async { tokio::time::sleep(Duration::from_secs(3600)).await }),
+            )]),
+            capabilities: Capabilities::detect(),
+            store,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        };
+
+        f.handle_session_close(close_event("abcd")).await;
+
+        assert!(f.tasks.is_empty());
+    }
+
+    /// Unique path under the system temp dir, so concurrent test runs don't collide.
+    fn test_store_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "edgehog-forwarder-{name}-{}.json",
+            std::process::id()
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_file_session_store_concurrent_save() {
+        let path = test_store_path("concurrent-save");
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let store = FileSessionStore::new(&path);
+
+        let sinfo_a = SessionInfo {
+            host: Ipv4Addr::LOCALHOST.to_string(),
+            port: 8080,
+            session_token: "a".to_string(),
+            secure: false,
+        };
+        let sinfo_b = SessionInfo {
+            host: Ipv4Addr::LOCALHOST.to_string(),
+            port: 8081,
+            session_token: "b".to_string(),
+            secure: false,
+        };
+
+        // two sessions saving their state concurrently, each through its own clone of the store
+        // sharing the same backing file, must not clobber one another's record
+        let (res_a, res_b) = tokio::join!(
+            store.clone().save(SessionRecord::new(
+                &sinfo_a,
+                Operation::Terminal,
+                SessionStatus::Connecting
+            )),
+            store.clone().save(SessionRecord::new(
+                &sinfo_b,
+                Operation::Terminal,
+                SessionStatus::Connecting
+            ))
+        );
+
+        assert!(res_a.is_ok());
+        assert!(res_b.is_ok());
+
+        let mut tokens: Vec<_> = store
+            .load()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.token)
+            .collect();
+        tokens.sort();
+
+        assert_eq!(tokens, vec!["a".to_string(), "b".to_string()]);
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn test_file_session_store_load_corrupt() {
+        let path = test_store_path("load-corrupt");
+
+        tokio::fs::write(&path, b"not valid json").await.unwrap();
+
+        let store = FileSessionStore::new(&path);
+
+        // a truncated or corrupt store degrades to empty instead of bricking startup
+        assert_eq!(store.load().await.unwrap(), Vec::new());
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
 }